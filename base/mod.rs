@@ -6,10 +6,18 @@ use std::collections::btree_map::Entry as BTreeMapEntry;
 /// Package path module.
 mod path;
 
+/// Object transaction module.
+mod objtrans;
+
+/// Visibility and effective-visibility computation.
+mod visibility;
+
 pub use self::path::Path;
-pub use self::path::PathConstructor;
 pub use self::path::PathIter;
-pub use self::path::PathNode;
+
+pub use self::objtrans::ObjectTransaction;
+pub use self::objtrans::TransactionError;
+pub use self::visibility::{Visibility, ItemRef, can_access};
 
 pub type ServiceMapEntry<'a> = BTreeMapEntry<
         'a, NameWrap, Box<ServiceArch>>;
@@ -43,6 +51,36 @@ pub struct Object {
     /// Service names tree. Allows to quickly find whether the service
     /// with given name already exist and access it.
     srvnames    : BTreeMap<NameWrap, ServiceMapEntry<'static>>,
+
+    /// Public sub-objects, keyed by name.
+    pubsub      : BTreeMap<String, Object>,
+
+    /// Internal sub-objects, keyed by name.
+    intsub      : BTreeMap<String, Object>,
+
+    /// Private sub-objects, keyed by name.
+    privsub     : BTreeMap<String, Object>,
+
+    /// Restriction path of every `Restricted` service, keyed by name.
+    /// Restricted items are otherwise stored as private, this just
+    /// records the extra `pub(in path)`-style scoping. Kept separate from
+    /// `subrestrictions` because services and sub-objects are distinct
+    /// namespaces -- an `Object` may have a service and a sub-object that
+    /// share a name.
+    srvrestrictions: BTreeMap<String, Path>,
+
+    /// Restriction path of every `Restricted` sub-object, keyed by name.
+    /// See `srvrestrictions`.
+    subrestrictions: BTreeMap<String, Path>,
+}
+
+/// Which of the three visibility-keyed containers of an `Object` an item
+/// (service or sub-object) currently occupies.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Tier {
+    Public,
+    Internal,
+    Private,
 }
 
 /// Object and it's ID in the network. Allows to distinguish objects with
@@ -56,7 +94,7 @@ pub struct ObjectVector {
 /// functionality gets needed, master reads the interface information
 /// and finds appropriate object that implements this interface and thus can
 /// solve some task with implemented interface functions.
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Interface {
 
     name    : String,
@@ -68,7 +106,7 @@ pub struct Interface {
 
 /// Package contains set of interfaces that solve similar tasks or have
 /// same vendor.
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Package {
 
     path    : Path,
@@ -76,7 +114,7 @@ pub struct Package {
 
 /// Service is called when some object needs to solve some problem which
 /// this service can carry out.
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Service {
 
     name    : String,
@@ -85,7 +123,7 @@ pub struct Service {
 /// Dependency on iterface implementation. Shows what interfaces should
 /// be implemented in order to allow some other interface to be
 /// implemented by same object.
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct InterfaceDependency {
 
     tree    : BTreeSet<Rc<Interface>>,
@@ -107,12 +145,201 @@ pub trait ServiceArch {
     fn service(&self) -> &Service;
 }
 
+impl PartialEq for ServiceArch {
+
+    fn eq(&self, other: &ServiceArch) -> bool {
+        self.service() == other.service()
+    }
+}
+
+impl Eq for ServiceArch {}
+
+impl PartialOrd for ServiceArch {
+
+    fn partial_cmp(&self, other: &ServiceArch) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ServiceArch {
+
+    fn cmp(&self, other: &ServiceArch) -> Ordering {
+        self.service().cmp(other.service())
+    }
+}
+
 impl Object {
 
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
 
+    /// All public sub-objects one step lower in hierarchy.
+    pub fn public_sub_objects(&self) -> &BTreeMap<String, Object> {
+        &self.pubsub
+    }
+
+    /// All internal sub-objects one step lower in hierarchy.
+    pub fn internal_sub_objects(&self) -> &BTreeMap<String, Object> {
+        &self.intsub
+    }
+
+    /// All private sub-objects one step lower in hierarchy.
+    pub fn private_sub_objects(&self) -> &BTreeMap<String, Object> {
+        &self.privsub
+    }
+
+    /// Whether a sub-object with this name already exists, at any
+    /// visibility level.
+    pub fn has_sub_object_with_name(&self, name: &str) -> bool {
+        self.find_sub_object_tier(name).is_some()
+    }
+
+    /// The tier (public/internal/private) the named service currently
+    /// belongs to, if it exists in this object at all.
+    pub(crate) fn find_service_tier(&self, name: &str) -> Option<Tier> {
+        if self.pubsrv.iter().any(|s| s.service().name() == name) {
+            Some(Tier::Public)
+        } else if self.intsrv.iter().any(|s| s.service().name() == name) {
+            Some(Tier::Internal)
+        } else if self.privsrv.iter().any(|s| s.service().name() == name) {
+            Some(Tier::Private)
+        } else {
+            None
+        }
+    }
+
+    /// The tier (public/internal/private) the named sub-object currently
+    /// belongs to, if it exists in this object at all.
+    pub(crate) fn find_sub_object_tier(&self, name: &str) -> Option<Tier> {
+        if self.pubsub.contains_key(name) {
+            Some(Tier::Public)
+        } else if self.intsub.contains_key(name) {
+            Some(Tier::Internal)
+        } else if self.privsub.contains_key(name) {
+            Some(Tier::Private)
+        } else {
+            None
+        }
+    }
+
+    fn srv_set_mut(&mut self, tier: Tier) -> &mut BTreeSet<Box<ServiceArch>> {
+        match tier {
+            Tier::Public    => &mut self.pubsrv,
+            Tier::Internal  => &mut self.intsrv,
+            Tier::Private   => &mut self.privsrv,
+        }
+    }
+
+    fn sub_set_mut(&mut self, tier: Tier) -> &mut BTreeMap<String, Object> {
+        match tier {
+            Tier::Public    => &mut self.pubsub,
+            Tier::Internal  => &mut self.intsub,
+            Tier::Private   => &mut self.privsub,
+        }
+    }
+
+    fn sub_set(&self, tier: Tier) -> &BTreeMap<String, Object> {
+        match tier {
+            Tier::Public    => &self.pubsub,
+            Tier::Internal  => &self.intsub,
+            Tier::Private   => &self.privsub,
+        }
+    }
+
+    /// The sub-object named `name`, stored at the given tier, if any.
+    pub(crate) fn sub_object_at(&self, tier: Tier, name: &str) -> Option<&Object> {
+        self.sub_set(tier).get(name)
+    }
+
+    /// Insert a service into the set for the given tier. `restriction`
+    /// records the `pub(in path)`-style scope when the service's real
+    /// declared visibility is `Visibility::Restricted`; restricted
+    /// services are otherwise stored as private (`Tier::Private`).
+    pub(crate) fn insert_service(&mut self, tier: Tier, srv: Box<ServiceArch>,
+            restriction: Option<Path>) {
+        let name = srv.service().name().clone();
+        self.srv_set_mut(tier).insert(srv);
+
+        if let Some(path) = restriction {
+            self.srvrestrictions.insert(name, path);
+        }
+    }
+
+    /// Remove and return the named service from the given tier's set, if
+    /// present there, along with its restriction path if it was
+    /// `Restricted`.
+    pub(crate) fn take_service(&mut self, tier: Tier, name: &str)
+            -> Option<(Box<ServiceArch>, Option<Path>)> {
+        let set = self.srv_set_mut(tier);
+        let old = ::std::mem::replace(set, BTreeSet::new());
+
+        let mut taken = None;
+        let mut remaining = BTreeSet::new();
+        for srv in old {
+            if taken.is_none() && srv.service().name() == name {
+                taken = Some(srv);
+            } else {
+                remaining.insert(srv);
+            }
+        }
+
+        *set = remaining;
+        taken.map(|srv| (srv, self.srvrestrictions.remove(name)))
+    }
+
+    /// Insert a sub-object into the set for the given tier. `restriction`
+    /// records the `pub(in path)`-style scope when the sub-object's real
+    /// declared visibility is `Visibility::Restricted`; restricted
+    /// sub-objects are otherwise stored as private (`Tier::Private`).
+    pub(crate) fn insert_sub_object(&mut self, tier: Tier, obj: Object,
+            restriction: Option<Path>) {
+        let name = obj.name.clone();
+        self.sub_set_mut(tier).insert(name.clone(), obj);
+
+        if let Some(path) = restriction {
+            self.subrestrictions.insert(name, path);
+        }
+    }
+
+    /// Remove and return the named sub-object from the given tier's set,
+    /// if present there, along with its restriction path if it was
+    /// `Restricted`.
+    pub(crate) fn take_sub_object(&mut self, tier: Tier, name: &str)
+            -> Option<(Object, Option<Path>)> {
+        self.sub_set_mut(tier).remove(name)
+            .map(|obj| (obj, self.subrestrictions.remove(name)))
+    }
+
+    /// The declared visibility of the named service, or `None` if no
+    /// service with that name exists in this object.
+    pub(crate) fn declared_service_visibility(&self, name: &str) -> Option<Visibility> {
+        let tier = self.find_service_tier(name)?;
+
+        if tier == Tier::Private {
+            if let Some(path) = self.srvrestrictions.get(name) {
+                return Some(Visibility::Restricted(path.clone()));
+            }
+        }
+
+        Some(self::visibility::tier_to_visibility(tier))
+    }
+
+    /// The declared visibility and storage tier of the named sub-object,
+    /// or `None` if no sub-object with that name exists in this object.
+    pub(crate) fn declared_sub_object_visibility(&self, name: &str)
+            -> Option<(Tier, Visibility)> {
+        let tier = self.find_sub_object_tier(name)?;
+
+        if tier == Tier::Private {
+            if let Some(path) = self.subrestrictions.get(name) {
+                return Some((tier, Visibility::Restricted(path.clone())));
+            }
+        }
+
+        Some((tier, self::visibility::tier_to_visibility(tier)))
+    }
+
     /// All public services at current network level of the object.
     pub fn public_services(&self) -> &BTreeSet<Box<ServiceArch>> {
         &self.pubsrv
@@ -133,6 +360,11 @@ impl Object {
         &self.ints
     }
 
+    /// Mutable access to the set of interfaces implemented by this object.
+    pub(crate) fn interfaces_mut(&mut self) -> &mut BTreeSet<Interface> {
+        &mut self.ints
+    }
+
     /// Whether this object has service with this name.
     pub fn has_service_with_name(&self, name: &String) -> bool {
         let val = self.srvnames.get(name);
@@ -312,6 +544,16 @@ impl Service {
     }
 }
 
+/// A bare `Service` is its own (architecture-less) `ServiceArch`, so that
+/// callers who don't need any architecture-specific data -- notably
+/// `ObjectTransaction`'s commands -- can box a plain `Service` directly.
+impl ServiceArch for Service {
+
+    fn service(&self) -> &Service {
+        self
+    }
+}
+
 impl InterfaceDependency {
 
     /// Create new InterfaceDependency with empty dependency list.
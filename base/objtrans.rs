@@ -1,4 +1,4 @@
-use super::{Service, Interface, Object};
+use super::{Service, Interface, Object, Path, Tier, Visibility};
 use std::collections::LinkedList;
 
 /// Transaction allows making multiple changes to object as a single
@@ -11,19 +11,6 @@ pub struct ObjectTransaction {
     cmds    : LinkedList<Command>,
 }
 
-/// Visibility of objects and services.
-pub enum Visibility {
-
-    /// Visible to everything from everywhere.
-    Public,
-
-    /// Visible only to services and sub-objects and their services.
-    Internal,
-
-    /// Visible only for parent object and services at current hierarchy level.
-    Private,
-}
-
 /// Commands used in object transactions.
 enum Command {
 
@@ -36,6 +23,9 @@ enum Command {
     /// Add private service.
     AddPrivSrv(Service),
 
+    /// Add a service visible only to the named object subtree.
+    AddRestrictedSrv(Service, Path),
+
     /// Remove public service.
     RemPubSrv(Service),
 
@@ -66,6 +56,9 @@ enum Command {
     /// New private sub-object.
     NewPrivSubObj(Object),
 
+    /// New sub-object visible only to the named object subtree.
+    NewRestrictedSubObj(Object, Path),
+
     /// Remove public sub-object.
     RemPubSubObj(Object),
 
@@ -94,6 +87,408 @@ enum Command {
     UnimplInt(Interface),
 }
 
+/// Record of a single mutation actually performed while applying a
+/// transaction, kept around so it can be undone if a later command in the
+/// same transaction fails to apply.
+enum Applied {
+    AddedSrv(Tier, String),
+    RemovedSrv(Tier, Box<super::ServiceArch>, Option<Path>),
+    ChangedSrvVis { from: Tier, to: Tier, name: String, old_restriction: Option<Path> },
+    AddedSub(Tier, String),
+    RemovedSub(Tier, Object, Option<Path>),
+    ChangedSubVis { from: Tier, to: Tier, name: String, old_restriction: Option<Path> },
+    Implemented(Interface),
+    Unimplemented(Interface),
+}
+
+/// Error which occurs when a transaction failed to be applied to an
+/// object. Names the command that could not be applied and the reason
+/// why, so the caller can report a precise diagnostic.
+pub struct TransactionError {
+
+    /// Textual description of the command that failed to apply.
+    cmd     : String,
+
+    /// Why the command could not be applied.
+    reason  : String,
+}
+
+impl TransactionError {
+
+    fn new(cmd: String, reason: String) -> Self {
+        TransactionError { cmd, reason }
+    }
+
+    /// Description of the command that could not be applied.
+    pub fn command(&self) -> &str {
+        &self.cmd
+    }
+
+    /// Reason the command could not be applied.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+/// The storage tier a command's target visibility maps to, together with
+/// the restriction path to record alongside it when that visibility is
+/// `Restricted`. Restricted items are stored as private.
+fn bucket_of(vis: &Visibility) -> (Tier, Option<Path>) {
+    match *vis {
+        Visibility::Public              => (Tier::Public, None),
+        Visibility::Internal            => (Tier::Internal, None),
+        Visibility::Private             => (Tier::Private, None),
+        Visibility::Restricted(ref p)   => (Tier::Private, Some(p.clone())),
+    }
+}
+
+fn describe(cmd: &Command) -> String {
+    use self::Command::*;
+
+    match *cmd {
+        AddPubSrv(ref s) => format!("add public service '{}'", s.name()),
+        AddIntSrv(ref s) => format!("add internal service '{}'", s.name()),
+        AddPrivSrv(ref s) => format!("add private service '{}'", s.name()),
+        AddRestrictedSrv(ref s, _) =>
+            format!("add restricted service '{}'", s.name()),
+        RemPubSrv(ref s) => format!("remove public service '{}'", s.name()),
+        RemIntSrv(ref s) => format!("remove internal service '{}'", s.name()),
+        RemPrivSrv(ref s) => format!("remove private service '{}'", s.name()),
+        ChgPubSrvVis(ref s, _) =>
+            format!("change visibility of public service '{}'", s.name()),
+        ChgIntSrvVis(ref s, _) =>
+            format!("change visibility of internal service '{}'", s.name()),
+        ChgPrivSrvVis(ref s, _) =>
+            format!("change visibility of private service '{}'", s.name()),
+        ChgSrvVis(ref s, _) =>
+            format!("change visibility of service '{}'", s.name()),
+        NewPubSubObj(ref o) => format!("create public sub-object '{}'", o.name()),
+        NewIntSubObj(ref o) => format!("create internal sub-object '{}'", o.name()),
+        NewPrivSubObj(ref o) => format!("create private sub-object '{}'", o.name()),
+        NewRestrictedSubObj(ref o, _) =>
+            format!("create restricted sub-object '{}'", o.name()),
+        RemPubSubObj(ref o) => format!("remove public sub-object '{}'", o.name()),
+        RemIntSubObj(ref o) => format!("remove internal sub-object '{}'", o.name()),
+        RemPrivSubObj(ref o) => format!("remove private sub-object '{}'", o.name()),
+        ChgSubObjVis(ref o, _) =>
+            format!("change visibility of sub-object '{}'", o.name()),
+        ChgPubSubObjVis(ref o, _) =>
+            format!("change visibility of public sub-object '{}'", o.name()),
+        ChgIntSubObjVis(ref o, _) =>
+            format!("change visibility of internal sub-object '{}'", o.name()),
+        ChgPrivSubObjVis(ref o, _) =>
+            format!("change visibility of private sub-object '{}'", o.name()),
+        ImplInt(ref i) => format!("implement interface '{}'", i.name()),
+        UnimplInt(ref i) => format!("unimplement interface '{}'", i.name()),
+    }
+}
+
+fn require(cond: bool, cmd: &Command, reason: &str) -> Result<(), TransactionError> {
+    if cond {
+        Ok(())
+    } else {
+        Err(TransactionError::new(describe(cmd), reason.to_string()))
+    }
+}
+
+/// `Restricted(p)` is only a valid declaration for an item living at
+/// `at` if `p` is an ancestor of, or equal to, `at` itself -- just as
+/// `pub(in path)` requires `path` to be an ancestor module.
+fn require_restriction_in_scope(vis: &Visibility, at: &Path, cmd: &Command)
+        -> Result<(), TransactionError> {
+    match *vis {
+        Visibility::Restricted(ref p) => require(p.is_prefix_of(at), cmd,
+            "restriction path is not an ancestor of the object it applies to"),
+        _ => Ok(()),
+    }
+}
+
+/// Validate a single command against the current (unmodified) state of
+/// `obj`, which itself is located at `at`. Performs no mutation.
+fn validate(cmd: &Command, obj: &Object, at: &Path) -> Result<(), TransactionError> {
+    use self::Command::*;
+
+    match *cmd {
+        AddPubSrv(ref s) | AddIntSrv(ref s) | AddPrivSrv(ref s) =>
+            require(obj.find_service_tier(s.name()).is_none(), cmd,
+                "a service with this name already exists"),
+        AddRestrictedSrv(ref s, ref p) => {
+            require(obj.find_service_tier(s.name()).is_none(), cmd,
+                "a service with this name already exists")?;
+            require(p.is_prefix_of(at), cmd,
+                "restriction path is not an ancestor of the object it applies to")
+        },
+
+        RemPubSrv(ref s) =>
+            require(obj.find_service_tier(s.name()) == Some(Tier::Public), cmd,
+                "service is not a public service of this object"),
+        RemIntSrv(ref s) =>
+            require(obj.find_service_tier(s.name()) == Some(Tier::Internal), cmd,
+                "service is not an internal service of this object"),
+        RemPrivSrv(ref s) =>
+            require(obj.find_service_tier(s.name()) == Some(Tier::Private), cmd,
+                "service is not a private service of this object"),
+
+        ChgPubSrvVis(ref s, ref v) => {
+            require(obj.find_service_tier(s.name()) == Some(Tier::Public), cmd,
+                "service is not a public service of this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+        ChgIntSrvVis(ref s, ref v) => {
+            require(obj.find_service_tier(s.name()) == Some(Tier::Internal), cmd,
+                "service is not an internal service of this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+        ChgPrivSrvVis(ref s, ref v) => {
+            require(obj.find_service_tier(s.name()) == Some(Tier::Private), cmd,
+                "service is not a private service of this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+        ChgSrvVis(ref s, ref v) => {
+            require(obj.find_service_tier(s.name()).is_some(), cmd,
+                "service not found in this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+
+        NewPubSubObj(ref o) | NewIntSubObj(ref o) | NewPrivSubObj(ref o) =>
+            require(!obj.has_sub_object_with_name(o.name()), cmd,
+                "a sub-object with this name already exists"),
+        NewRestrictedSubObj(ref o, ref p) => {
+            require(!obj.has_sub_object_with_name(o.name()), cmd,
+                "a sub-object with this name already exists")?;
+            require(p.is_prefix_of(at), cmd,
+                "restriction path is not an ancestor of the object it applies to")
+        },
+
+        RemPubSubObj(ref o) =>
+            require(obj.find_sub_object_tier(o.name()) == Some(Tier::Public), cmd,
+                "sub-object is not a public sub-object of this object"),
+        RemIntSubObj(ref o) =>
+            require(obj.find_sub_object_tier(o.name()) == Some(Tier::Internal), cmd,
+                "sub-object is not an internal sub-object of this object"),
+        RemPrivSubObj(ref o) =>
+            require(obj.find_sub_object_tier(o.name()) == Some(Tier::Private), cmd,
+                "sub-object is not a private sub-object of this object"),
+
+        ChgSubObjVis(ref o, ref v) => {
+            require(obj.find_sub_object_tier(o.name()).is_some(), cmd,
+                "sub-object not found in this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+        ChgPubSubObjVis(ref o, ref v) => {
+            require(obj.find_sub_object_tier(o.name()) == Some(Tier::Public), cmd,
+                "sub-object is not a public sub-object of this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+        ChgIntSubObjVis(ref o, ref v) => {
+            require(obj.find_sub_object_tier(o.name()) == Some(Tier::Internal), cmd,
+                "sub-object is not an internal sub-object of this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+        ChgPrivSubObjVis(ref o, ref v) => {
+            require(obj.find_sub_object_tier(o.name()) == Some(Tier::Private), cmd,
+                "sub-object is not a private sub-object of this object")?;
+            require_restriction_in_scope(v, at, cmd)
+        },
+
+        ImplInt(ref i) =>
+            require(!obj.interfaces().contains(i), cmd,
+                "interface is already implemented"),
+        UnimplInt(ref i) =>
+            require(obj.interfaces().contains(i), cmd,
+                "interface is not implemented"),
+    }
+}
+
+/// Apply a single command to `obj`, returning a record of what was done
+/// on success, or the (reconstructed) command together with the failure
+/// reason so it can still be named in the resulting error.
+fn apply_one(cmd: Command, obj: &mut Object) -> Result<Applied, (Command, String)> {
+    use self::Command::*;
+
+    match cmd {
+        AddPubSrv(s) => add_srv(obj, Tier::Public, s, AddPubSrv),
+        AddIntSrv(s) => add_srv(obj, Tier::Internal, s, AddIntSrv),
+        AddPrivSrv(s) => add_srv(obj, Tier::Private, s, AddPrivSrv),
+        AddRestrictedSrv(s, path) => add_restricted_srv(obj, s, path),
+
+        RemPubSrv(s) => remove_srv(obj, Tier::Public, s, RemPubSrv),
+        RemIntSrv(s) => remove_srv(obj, Tier::Internal, s, RemIntSrv),
+        RemPrivSrv(s) => remove_srv(obj, Tier::Private, s, RemPrivSrv),
+
+        ChgPubSrvVis(s, v) => change_srv_vis(obj, Tier::Public, s, v, ChgPubSrvVis),
+        ChgIntSrvVis(s, v) => change_srv_vis(obj, Tier::Internal, s, v, ChgIntSrvVis),
+        ChgPrivSrvVis(s, v) => change_srv_vis(obj, Tier::Private, s, v, ChgPrivSrvVis),
+        ChgSrvVis(s, v) => {
+            match obj.find_service_tier(s.name()) {
+                Some(from) => change_srv_vis(obj, from, s, v, ChgSrvVis),
+                None => Err((ChgSrvVis(s, v), "service no longer present".to_string())),
+            }
+        },
+
+        NewPubSubObj(o) => add_sub(obj, Tier::Public, o, NewPubSubObj),
+        NewIntSubObj(o) => add_sub(obj, Tier::Internal, o, NewIntSubObj),
+        NewPrivSubObj(o) => add_sub(obj, Tier::Private, o, NewPrivSubObj),
+        NewRestrictedSubObj(o, path) => add_restricted_sub(obj, o, path),
+
+        RemPubSubObj(o) => remove_sub(obj, Tier::Public, o, RemPubSubObj),
+        RemIntSubObj(o) => remove_sub(obj, Tier::Internal, o, RemIntSubObj),
+        RemPrivSubObj(o) => remove_sub(obj, Tier::Private, o, RemPrivSubObj),
+
+        ChgPubSubObjVis(o, v) => change_sub_vis(obj, Tier::Public, o, v, ChgPubSubObjVis),
+        ChgIntSubObjVis(o, v) => change_sub_vis(obj, Tier::Internal, o, v, ChgIntSubObjVis),
+        ChgPrivSubObjVis(o, v) => change_sub_vis(obj, Tier::Private, o, v, ChgPrivSubObjVis),
+        ChgSubObjVis(o, v) => {
+            match obj.find_sub_object_tier(o.name()) {
+                Some(from) => change_sub_vis(obj, from, o, v, ChgSubObjVis),
+                None => Err((ChgSubObjVis(o, v), "sub-object no longer present".to_string())),
+            }
+        },
+
+        ImplInt(i) => {
+            let record = i.clone();
+            obj.interfaces_mut().insert(i);
+            Ok(Applied::Implemented(record))
+        },
+        UnimplInt(i) => {
+            if obj.interfaces_mut().remove(&i) {
+                Ok(Applied::Unimplemented(i))
+            } else {
+                Err((UnimplInt(i), "interface no longer implemented".to_string()))
+            }
+        },
+    }
+}
+
+/// Add a service under the given tier, failing instead of silently
+/// overwriting if a service with this name is already present. Needed
+/// because `validate` only checks a command against the object's
+/// *original* state: two `Add*Srv` commands for the same name in one
+/// transaction both pass validation, so this check is what actually
+/// catches the collision once the first of them has been applied.
+fn add_srv(obj: &mut Object, tier: Tier, s: Service,
+        rebuild: fn(Service) -> Command) -> Result<Applied, (Command, String)> {
+    if obj.find_service_tier(s.name()).is_some() {
+        return Err((rebuild(s), "a service with this name already exists".to_string()));
+    }
+
+    let name = s.name().clone();
+    obj.insert_service(tier, Box::new(s), None);
+    Ok(Applied::AddedSrv(tier, name))
+}
+
+/// As `add_srv`, for a service restricted to the subtree rooted at `path`.
+fn add_restricted_srv(obj: &mut Object, s: Service, path: Path)
+        -> Result<Applied, (Command, String)> {
+    if obj.find_service_tier(s.name()).is_some() {
+        return Err((Command::AddRestrictedSrv(s, path),
+            "a service with this name already exists".to_string()));
+    }
+
+    let name = s.name().clone();
+    obj.insert_service(Tier::Private, Box::new(s), Some(path));
+    Ok(Applied::AddedSrv(Tier::Private, name))
+}
+
+fn remove_srv(obj: &mut Object, tier: Tier, s: Service,
+        rebuild: fn(Service) -> Command) -> Result<Applied, (Command, String)> {
+    match obj.take_service(tier, s.name()) {
+        Some((removed, restriction)) => Ok(Applied::RemovedSrv(tier, removed, restriction)),
+        None => Err((rebuild(s), "service no longer present".to_string())),
+    }
+}
+
+fn change_srv_vis(obj: &mut Object, from: Tier, s: Service, newvis: Visibility,
+        rebuild: fn(Service, Visibility) -> Command)
+        -> Result<Applied, (Command, String)> {
+    let (to, restriction) = bucket_of(&newvis);
+    let name = s.name().clone();
+
+    match obj.take_service(from, &name) {
+        Some((found, old_restriction)) => {
+            obj.insert_service(to, found, restriction);
+            Ok(Applied::ChangedSrvVis { from, to, name, old_restriction })
+        },
+        None => Err((rebuild(s, newvis), "service no longer present".to_string())),
+    }
+}
+
+/// Add a sub-object under the given tier, failing instead of silently
+/// overwriting if a sub-object with this name is already present. See
+/// `add_srv` for why this can't be left to `validate` alone.
+fn add_sub(obj: &mut Object, tier: Tier, o: Object,
+        rebuild: fn(Object) -> Command) -> Result<Applied, (Command, String)> {
+    if obj.has_sub_object_with_name(o.name()) {
+        return Err((rebuild(o), "a sub-object with this name already exists".to_string()));
+    }
+
+    let name = o.name().to_string();
+    obj.insert_sub_object(tier, o, None);
+    Ok(Applied::AddedSub(tier, name))
+}
+
+/// As `add_sub`, for a sub-object restricted to the subtree rooted at
+/// `path`.
+fn add_restricted_sub(obj: &mut Object, o: Object, path: Path)
+        -> Result<Applied, (Command, String)> {
+    if obj.has_sub_object_with_name(o.name()) {
+        return Err((Command::NewRestrictedSubObj(o, path),
+            "a sub-object with this name already exists".to_string()));
+    }
+
+    let name = o.name().to_string();
+    obj.insert_sub_object(Tier::Private, o, Some(path));
+    Ok(Applied::AddedSub(Tier::Private, name))
+}
+
+fn remove_sub(obj: &mut Object, tier: Tier, o: Object,
+        rebuild: fn(Object) -> Command) -> Result<Applied, (Command, String)> {
+    match obj.take_sub_object(tier, o.name()) {
+        Some((removed, restriction)) => Ok(Applied::RemovedSub(tier, removed, restriction)),
+        None => Err((rebuild(o), "sub-object no longer present".to_string())),
+    }
+}
+
+fn change_sub_vis(obj: &mut Object, from: Tier, o: Object, newvis: Visibility,
+        rebuild: fn(Object, Visibility) -> Command)
+        -> Result<Applied, (Command, String)> {
+    let (to, restriction) = bucket_of(&newvis);
+    let name = o.name().to_string();
+
+    match obj.take_sub_object(from, &name) {
+        Some((found, old_restriction)) => {
+            obj.insert_sub_object(to, found, restriction);
+            Ok(Applied::ChangedSubVis { from, to, name, old_restriction })
+        },
+        None => Err((rebuild(o, newvis), "sub-object no longer present".to_string())),
+    }
+}
+
+/// Undo a single already-applied mutation, restoring `obj` to how it was
+/// before that mutation happened.
+fn undo_one(applied: Applied, obj: &mut Object) {
+    match applied {
+        Applied::AddedSrv(tier, name) => { obj.take_service(tier, &name); },
+        Applied::RemovedSrv(tier, srv, restriction) =>
+            obj.insert_service(tier, srv, restriction),
+        Applied::ChangedSrvVis { from, to, name, old_restriction } => {
+            if let Some((srv, _)) = obj.take_service(to, &name) {
+                obj.insert_service(from, srv, old_restriction);
+            }
+        },
+        Applied::AddedSub(tier, name) => { obj.take_sub_object(tier, &name); },
+        Applied::RemovedSub(tier, sub, restriction) =>
+            obj.insert_sub_object(tier, sub, restriction),
+        Applied::ChangedSubVis { from, to, name, old_restriction } => {
+            if let Some((sub, _)) = obj.take_sub_object(to, &name) {
+                obj.insert_sub_object(from, sub, old_restriction);
+            }
+        },
+        Applied::Implemented(i) => { obj.interfaces_mut().remove(&i); },
+        Applied::Unimplemented(i) => { obj.interfaces_mut().insert(i); },
+    }
+}
+
 impl ObjectTransaction {
 
     pub fn new() -> Self {
@@ -117,14 +512,21 @@ impl ObjectTransaction {
         self.pushcmd(Command::AddPrivSrv(srv));
     }
 
+    /// Add a new service visible only to the object subtree rooted at
+    /// `path`.
+    pub fn add_restricted_service(&mut self, srv: Service, path: Path) {
+        self.pushcmd(Command::AddRestrictedSrv(srv, path));
+    }
+
     /// Add service and set it's visibility to given value.
     pub fn add_service(&mut self, srv: Service, vis: Visibility) {
         use self::Visibility::*;
 
         match vis {
-            Public      => self.add_public_service(srv),
-            Private     => self.add_internal_service(srv),
-            Internal    => self.add_internal_service(srv),
+            Public              => self.add_public_service(srv),
+            Private             => self.add_private_service(srv),
+            Internal            => self.add_internal_service(srv),
+            Restricted(path)    => self.add_restricted_service(srv, path),
         }
     }
 
@@ -147,13 +549,16 @@ impl ObjectTransaction {
             oldvis: Option<Visibility>) {
         use self::Visibility::*;
 
-        if oldvis.is_none() {
-            self.pushcmd(Command::ChgSrvVis(srv, newvis));
-        } else { match oldvis.unwrap() {
-            Public      => self.pushcmd(Command::ChgPubSrvVis(srv, newvis)),
-            Private     => self.pushcmd(Command::ChgPrivSrvVis(srv, newvis)),
-            Internal    => self.pushcmd(Command::ChgIntSrvVis(srv, newvis)),
-        }}
+        match oldvis {
+            None                => self.pushcmd(Command::ChgSrvVis(srv, newvis)),
+            Some(Public)        => self.pushcmd(Command::ChgPubSrvVis(srv, newvis)),
+            Some(Private)       => self.pushcmd(Command::ChgPrivSrvVis(srv, newvis)),
+            Some(Internal)      => self.pushcmd(Command::ChgIntSrvVis(srv, newvis)),
+            // A restricted service is physically stored as private; there
+            // is no dedicated bucket to target it directly, so fall back
+            // to searching every bucket for it by name.
+            Some(Restricted(_)) => self.pushcmd(Command::ChgSrvVis(srv, newvis)),
+        }
     }
 
     /// Create new public sub-object.
@@ -171,6 +576,12 @@ impl ObjectTransaction {
         self.pushcmd(Command::NewPrivSubObj(obj));
     }
 
+    /// Create a new sub-object visible only to the object subtree rooted
+    /// at `path`.
+    pub fn new_restricted_sub_object(&mut self, obj: Object, path: Path) {
+        self.pushcmd(Command::NewRestrictedSubObj(obj, path));
+    }
+
     /// Delete private sub-object.
     pub fn remove_private_sub_object(&mut self, obj: Object) {
         self.pushcmd(Command::RemPrivSubObj(obj));
@@ -191,26 +602,17 @@ impl ObjectTransaction {
     /// checked to find it and change the visibility to appropriate.
     pub fn change_sub_object_access(&mut self, obj: Object, newvis: Visibility,
             oldvis: Option<Visibility>) {
-         use self::Command::{
-            ChgSubObjVis,
-            ChgPrivSubObjVis,
-            ChgPubSubObjVis,
-            ChgIntSubObjVis,
-         };
-
          use self::Visibility::*;
 
-         self.pushcmd(
-            if oldvis.is_none() {
-                ChgSubObjVis(obj, newvis)
-            } else {
-                match oldvis.unwrap() {
-                    Public      => ChgPubSubObjVis(obj, newvis),
-                    Private     => ChgPrivSubObjVis(obj, newvis),
-                    Internal    => ChgIntSubObjVis(obj, newvis),
-                }
-            }
-         );
+         self.pushcmd(match oldvis {
+            None                => Command::ChgSubObjVis(obj, newvis),
+            Some(Public)        => Command::ChgPubSubObjVis(obj, newvis),
+            Some(Private)       => Command::ChgPrivSubObjVis(obj, newvis),
+            Some(Internal)      => Command::ChgIntSubObjVis(obj, newvis),
+            // Same reasoning as `change_service_access`: restricted
+            // sub-objects share the private bucket, so search for them.
+            Some(Restricted(_)) => Command::ChgSubObjVis(obj, newvis),
+         });
     }
 
     /// Mark this object as interface implementer.
@@ -227,12 +629,193 @@ impl ObjectTransaction {
         self.cmds.push_front(cmd)
     }
 
-    /// Applies changes to given object.
-    pub fn apply_to_object(&self, obj: &mut Object) {
-        use self::Command::*;
+    /// Applies changes to given object, located at `at` in the object
+    /// hierarchy, as a single atomic operation: every command is first
+    /// validated against the object's current state without mutating
+    /// anything, and only once the whole list passes validation are the
+    /// commands actually applied, in the order they were originally
+    /// submitted (`pushcmd` uses `push_front`, so that order is the
+    /// reverse of `cmds`' own iteration order).
+    ///
+    /// Commands can still be individually valid yet conflict with one
+    /// another once earlier commands in the same transaction have been
+    /// applied (e.g. two commands creating a sub-object of the same
+    /// name). Should that happen, every mutation already performed by
+    /// this call is undone before the error is returned, so `obj` is left
+    /// exactly as it was found.
+    pub fn apply_to_object(&mut self, obj: &mut Object, at: &Path)
+            -> Result<(), TransactionError> {
+        for cmd in self.cmds.iter().rev() {
+            validate(cmd, obj, at)?;
+        }
 
-        for cmd in self.cmds.iter() {
-            unimplemented!()
+        let pending: Vec<Command> =
+            ::std::mem::replace(&mut self.cmds, LinkedList::new())
+                .into_iter().rev().collect();
+
+        let mut applied = Vec::with_capacity(pending.len());
+
+        for cmd in pending {
+            match apply_one(cmd, obj) {
+                Ok(record) => applied.push(record),
+                Err((cmd, reason)) => {
+                    for record in applied.into_iter().rev() {
+                        undo_one(record, obj);
+                    }
+                    return Err(TransactionError::new(describe(&cmd), reason));
+                },
+            }
         }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{InterfaceDependency, InterfaceVersion, Package};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::rc::Rc;
+
+    /// Object with every collection empty, named `name`.
+    fn empty_object(name: &str) -> Object {
+        Object {
+            name            : name.to_string(),
+            pubsrv          : BTreeSet::new(),
+            intsrv          : BTreeSet::new(),
+            privsrv         : BTreeSet::new(),
+            ints            : BTreeSet::new(),
+            srvnames        : BTreeMap::new(),
+            pubsub          : BTreeMap::new(),
+            intsub          : BTreeMap::new(),
+            privsub         : BTreeMap::new(),
+            srvrestrictions : BTreeMap::new(),
+            subrestrictions : BTreeMap::new(),
+        }
+    }
+
+    fn svc(name: &str) -> Service {
+        Service { name: name.to_string() }
+    }
+
+    /// Minimal interface with no dependencies or member services, located
+    /// at `at`.
+    fn iface(name: &str, at: &Path) -> Interface {
+        Interface {
+            name    : name.to_string(),
+            dep     : InterfaceDependency::new(),
+            ver     : InterfaceVersion::new(1, 0, 0),
+            pack    : Rc::new(Package { path: at.clone() }),
+            serv    : BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn validate_rejects_duplicate_sub_object_without_mutating() {
+        let mut parent = empty_object("parent");
+        let at = Path::new("parent".to_string());
+        parent.insert_sub_object(Tier::Public, empty_object("child"), None);
+
+        let mut txn = ObjectTransaction::new();
+        txn.new_public_sub_object(empty_object("child"));
+
+        let err = txn.apply_to_object(&mut parent, &at)
+            .expect_err("duplicate sub-object must be rejected");
+        assert!(err.reason().contains("already exists"));
+
+        assert_eq!(parent.public_sub_objects().len(), 1);
+        assert!(parent.find_sub_object_tier("child") == Some(Tier::Public));
+    }
+
+    /// `validate` only checks each command against the object's original
+    /// state, so two `new_public_sub_object` commands creating the same
+    /// name in a single transaction both pass validation. It's `add_sub`,
+    /// applied during the second (mutation) phase, that must catch the
+    /// collision and trigger a full rollback -- not silently let
+    /// `BTreeMap::insert` overwrite the first one.
+    #[test]
+    fn apply_rejects_duplicate_sub_object_created_within_same_transaction() {
+        let mut parent = empty_object("parent");
+        let at = Path::new("parent".to_string());
+
+        let mut child_a = empty_object("child");
+        child_a.insert_service(Tier::Public, Box::new(svc("marker_a")), None);
+
+        let mut child_b = empty_object("child");
+        child_b.insert_service(Tier::Public, Box::new(svc("marker_b")), None);
+
+        let mut txn = ObjectTransaction::new();
+        txn.new_public_sub_object(child_a);
+        txn.new_public_sub_object(child_b);
+
+        let err = txn.apply_to_object(&mut parent, &at)
+            .expect_err("creating two sub-objects with the same name must be rejected");
+        assert!(err.reason().contains("already exists"));
+
+        // The first sub-object's insertion must have been rolled back too,
+        // not just rejection of the second.
+        assert_eq!(parent.public_sub_objects().len(), 0);
+    }
+
+    #[test]
+    fn validate_rejects_removing_nonexistent_service_without_mutating() {
+        let mut parent = empty_object("parent");
+        let at = Path::new("parent".to_string());
+        parent.insert_service(Tier::Public, Box::new(svc("kept")), None);
+
+        let mut txn = ObjectTransaction::new();
+        txn.remove_public_service(svc("ghost"));
+
+        let err = txn.apply_to_object(&mut parent, &at)
+            .expect_err("removing an absent service must be rejected");
+        assert!(err.reason().contains("not a public service"));
+
+        assert_eq!(parent.public_services().len(), 1);
+        assert!(parent.find_service_tier("kept") == Some(Tier::Public));
+    }
+
+    #[test]
+    fn validate_rejects_reimplementing_interface_without_mutating() {
+        let mut parent = empty_object("parent");
+        let at = Path::new("parent".to_string());
+        let i = iface("Iface", &at);
+        parent.interfaces_mut().insert(i.clone());
+
+        let mut txn = ObjectTransaction::new();
+        txn.implement_interface(i.clone());
+
+        let err = txn.apply_to_object(&mut parent, &at)
+            .expect_err("re-implementing an interface must be rejected");
+        assert!(err.reason().contains("already implemented"));
+
+        assert_eq!(parent.interfaces().len(), 1);
+        assert!(parent.interfaces().contains(&i));
+    }
+
+    /// Two commands can each be individually valid against the object's
+    /// starting state yet conflict once the first of them has actually
+    /// been applied (here, removing the same service twice). The second
+    /// command's failure must unwind every mutation already performed by
+    /// this call -- including unrelated ones earlier in the same
+    /// transaction -- leaving `obj` exactly as it was found.
+    #[test]
+    fn apply_failure_rolls_back_every_already_applied_command() {
+        let mut parent = empty_object("parent");
+        let at = Path::new("parent".to_string());
+        parent.insert_service(Tier::Public, Box::new(svc("shared")), None);
+
+        let mut txn = ObjectTransaction::new();
+        txn.add_public_service(svc("new"));
+        txn.remove_public_service(svc("shared"));
+        txn.remove_public_service(svc("shared"));
+
+        let err = txn.apply_to_object(&mut parent, &at)
+            .expect_err("removing the same service twice must fail");
+        assert_eq!(err.reason(), "service no longer present");
+
+        assert!(parent.find_service_tier("new") == None);
+        assert!(parent.find_service_tier("shared") == Some(Tier::Public));
+        assert_eq!(parent.public_services().len(), 1);
     }
 }
@@ -1,41 +1,115 @@
 use std::rc::Rc;
-use std::iter::*;
-use std::collections::LinkedList;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::iter::FusedIterator;
 use std::cmp::Ordering;
 
+/// An interned node-name string. Cheap to copy and compare; the backing
+/// `String` lives once in the owning `PathArena`, no matter how many
+/// path nodes share the same name.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Symbol(u32);
+
+/// Handle to a single node stored in a `PathArena`. Cheap to copy, and
+/// only meaningful together with the arena that produced it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PathId(u32);
+
+/// A single node of a path: an interned name and the node it is nested
+/// in, if any.
+struct PathNode {
+
+    /// Interned name of this node.
+    name    : Symbol,
+
+    /// The node this one is nested in, if any.
+    parent  : Option<PathId>,
+}
+
+/// Owns every path node created for one path hierarchy, and interns node
+/// name strings so that repeated package names are stored only once.
+/// Replaces the old model of one `Rc`-linked `Path` allocation per node
+/// (and the unsafe `String::from_raw_parts` clone that model needed for
+/// iteration) with a flat, allocation-light index that walks parent
+/// links by `PathId` instead.
+#[derive(Default)]
+struct PathArena {
+
+    /// Every node ever created, indexed by `PathId`.
+    nodes   : Vec<PathNode>,
+
+    /// Interned node name strings, indexed by `Symbol`.
+    strings : Vec<String>,
+
+    /// Reverse lookup from name to its `Symbol`, so a name already
+    /// present in `strings` is reused instead of stored again.
+    lookup  : HashMap<String, Symbol>,
+}
+
+impl PathArena {
+
+    fn intern(&mut self, name: String) -> Symbol {
+        if let Some(sym) = self.lookup.get(&name) {
+            return *sym;
+        }
+
+        let sym = Symbol(self.strings.len() as u32);
+        self.lookup.insert(name.clone(), sym);
+        self.strings.push(name);
+        sym
+    }
+
+    fn push(&mut self, name: String, parent: Option<PathId>) -> PathId {
+        let name = self.intern(name);
+        let id = PathId(self.nodes.len() as u32);
+        self.nodes.push(PathNode { name, parent });
+        id
+    }
+
+    fn name(&self, id: PathId) -> &str {
+        &self.strings[self.nodes[id.0 as usize].name.0 as usize]
+    }
+
+    fn parent(&self, id: PathId) -> Option<PathId> {
+        self.nodes[id.0 as usize].parent
+    }
+}
+
 /// The node of the path.
+///
+/// A `Path` is a handle into a `PathArena`: cloning it is just a
+/// refcount bump and a `PathId` copy, not a node allocation, and reading
+/// it never involves `unsafe` code.
 #[derive(Clone)]
 pub struct Path {
 
-    /// Package name at current hierarchy level.
-    name    : String,
+    /// Arena this node, and every one of its ancestors, is stored in.
+    /// Shared with every `Path` derived from this one.
+    arena   : Rc<RefCell<PathArena>>,
 
-    /// Previous path node if any.
-    prev    : Option<Rc<Path>>,
+    /// This node's own handle into `arena`.
+    id      : PathId,
 }
 
-/// Path nodes iterator.
-pub struct PathIter<'a> {
+/// Path nodes iterator, from a path's own node up to its root.
+pub struct PathIter {
 
-    /// Last node of the path from which this iterator was created.
-    end     : &'a Path,
+    /// Arena shared with the path this iterator was created from.
+    arena   : Rc<RefCell<PathArena>>,
 
-    /// Current node.
-    cur     : Option<Rc<Path>>,
+    /// Current node, if iteration hasn't reached past the root yet.
+    cur     : Option<PathId>,
 }
 
-/// Bidirectional path iterator.
-pub struct BiPathIter<'a> {
+/// Bidirectional path iterator, over a path's nodes in root-to-leaf
+/// order.
+pub struct BiPathIter {
 
-    /// Whole path in array.
-    path    : Vec<Rc<Path>>,
+    /// Whole path, root-to-leaf.
+    chain   : Vec<PathId>,
 
-    /// The end path part from which this iterator was generated has
-    /// limited lifetime and as the last Rc in 'path' field was created
-    /// using non-full unsafe cloning of Path parent instance we must
-    /// guarantee that parent outlives this iterator so we could not
-    /// refer to non-existent data.
-    parent_life: ::std::marker::PhantomData<&'a Path>,
+    /// Arena shared with the path this iterator was created from.
+    arena   : Rc<RefCell<PathArena>>,
 
     /// Front position of the iterator.
     front   : usize,
@@ -48,17 +122,7 @@ pub struct BiPathIter<'a> {
 impl PartialEq for Path {
 
     fn eq(&self, other: &Path) -> bool {
-        if self.prev.is_some() {
-            if other.prev.is_none() {
-                false
-            } else {
-                other.prev.clone().unwrap() == self.prev.clone().unwrap()
-            }
-        } else if other.prev.is_none() {
-            self.name == other.name
-        } else {
-            false
-        }
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -66,30 +130,22 @@ impl Eq for Path {}
 
 impl Ord for Path {
 
+    /// Lexicographic comparison in root-to-leaf order: the first node
+    /// name that differs decides the ordering, and if one path is a
+    /// proper prefix of the other the shorter one is `Less`. Equal only
+    /// when every node name matches and both paths have the same length.
     fn cmp(&self, other: &Path) -> Ordering {
-        use self::Ordering::*;
-
-        let mut i0 = self.iter();
-        let mut i1 = other.iter();
-
-        match self.iter().count().cmp(&other.iter().count()) {
-            Greater => Greater,
-            Less    => Less,
-            Equal   => loop {
-                let next = i0.next();
-                if next.is_none() {
-                    return next.unwrap().name.cmp(&other.name);
-                } else {
-                    let next = next.unwrap();
-
-                    return match next.cmp(&i1.next().unwrap()) {
-                        Greater => Greater,
-                        Less    => Less,
-                        Equal   => continue,
-                    };
-                }
+        let mine: Vec<Path> = self.bi_iter().collect();
+        let theirs: Vec<Path> = other.bi_iter().collect();
+
+        for (a, b) in mine.iter().zip(theirs.iter()) {
+            match a.name().cmp(&b.name()) {
+                Ordering::Equal => continue,
+                order => return order,
             }
         }
+
+        mine.len().cmp(&theirs.len())
     }
 }
 
@@ -104,160 +160,198 @@ impl Path {
 
     /// Generate new root node for given package name.
     pub fn new(name: String) -> Self {
-        Path {
-            name,
-            prev: None,
-        }
+        let arena = Rc::new(RefCell::new(PathArena::default()));
+        let id = arena.borrow_mut().push(name, None);
+        Path { arena, id }
     }
 
     /// Create new node at the end of given path.
-    pub fn new_in_path(name: String, prev: Rc<Path>) -> Self {
-        Path {
-            name,
-            prev: Some(prev),
-        }
+    pub fn new_in_path(name: String, prev: &Path) -> Self {
+        let id = prev.arena.borrow_mut().push(name, Some(prev.id));
+        Path { arena: prev.arena.clone(), id }
     }
 
     /// The name of the node.
-    pub fn name(&self) -> &String {
-        &self.name
+    pub fn name(&self) -> String {
+        self.arena.borrow().name(self.id).to_string()
     }
 
     /// Full path from this node to root node with given delimiter string.
     pub fn full_path(&self, delim: &str) -> String {
-        let length = {
-
-            // Delimiter repeat counter:
-            let mut delims = 0;
-
-            // Each node name length adds to this variable:
-            let mut strings = 0;
+        let chain: Vec<Path> = self.bi_iter().collect();
+        let mut fullpath = String::new();
 
-            for node in self.iter() {
-                strings += node.name.len();
-                delims += 1;
+        for (i, node) in chain.iter().enumerate() {
+            if i > 0 {
+                fullpath += delim;
             }
-
-            // Note: currently delimiter was counted as if after each node
-            // it is placed but in reality it is not placed after last one.
-            // Fix this by subtracting 1.
-            delims -= 1;
-
-            strings + delims * delim.len()
-        };
-
-        let mut fullpath = String::with_capacity(length);
-
-        for node in self.iter() {
-            fullpath += &node.name;
-            fullpath += delim;
+            fullpath += &node.name();
         }
 
         fullpath
     }
 
-    /// Iterator for current path.
-    pub fn iter<'a>(&'a self) -> PathIter<'a> {
+    /// Iterator for current path, walking from this node up to the root.
+    pub fn iter(&self) -> PathIter {
         PathIter {
-            end: self,
-            cur: Some(Rc::new(unsafe { self.clone_but_save_refs() })),
+            arena: self.arena.clone(),
+            cur: Some(self.id),
         }
     }
 
-    /// Bi-directional iterator for current path.
-    pub fn bi_iter<'a>(&'a self) -> BiPathIter<'a> {
-        let path = {
-            let len = self.iter().count();
-            let mut vec = Vec::with_capacity(len);
+    /// Whether `self` names the same object as `other` or one of its
+    /// ancestors, i.e. whether `self` is a valid `pub(in self)`-style
+    /// restriction path for something located at `other`.
+    pub fn is_prefix_of(&self, other: &Path) -> bool {
+        let mine: Vec<_> = self.bi_iter().collect();
+        let theirs: Vec<_> = other.bi_iter().collect();
 
-            for item in self.iter() {
-                vec.push(item.clone());
-            }
-            vec.reverse();
-            vec
-        };
+        if mine.len() > theirs.len() {
+            return false;
+        }
+
+        mine.iter().zip(theirs.iter()).all(|(a, b)| a.name() == b.name())
+    }
+
+    /// Bi-directional iterator for current path, in root-to-leaf order.
+    pub fn bi_iter(&self) -> BiPathIter {
+        let mut chain: Vec<PathId> = self.iter().map(|node| node.id).collect();
+        chain.reverse();
 
         BiPathIter {
-            path,
-            parent_life: Default::default(),
+            chain,
+            arena: self.arena.clone(),
             front: 0,
             back: 0,
         }
     }
-
-    /// Make a clone of this instance but don't clone the name. Instead,
-    /// create a String from raw parts to point to already allocated
-    /// name.
-    ///
-    /// # Safety
-    ///
-    /// When parent instance gets dropped then
-    /// this clone will refer to non-existent data.
-    unsafe fn clone_but_save_refs(&self) -> Self {
-        let name = {
-            let ptr = self.name.as_bytes().as_ptr() as _;
-            let length = self.name.len();
-            let capacity = self.name.capacity();
-            String::from_raw_parts(ptr, length, capacity)
-        };
-        Path {
-            name,
-            prev: self.prev.clone(),
-        }
-    }
 }
 
-impl<'a> Iterator for PathIter<'a> {
+impl Iterator for PathIter {
 
-    type Item = Rc<Path>;
+    type Item = Path;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let has_cur = self.cur.is_some();
-        if has_cur {
-            let result = self.cur.clone().unwrap();
-            self.cur = self.cur.clone().unwrap().prev.clone();
-            Some(result)
-        } else {
-            None
+        match self.cur {
+            Some(id) => {
+                self.cur = self.arena.borrow().parent(id);
+                Some(Path { arena: self.arena.clone(), id })
+            },
+            None => None,
         }
     }
 }
 
-impl<'a> FusedIterator for PathIter<'a> {}
+impl FusedIterator for PathIter {}
 
-impl<'a> Iterator for BiPathIter<'a> {
+impl Iterator for BiPathIter {
 
-    type Item = Rc<Path>;
+    type Item = Path;
 
-    fn next(&mut self) -> Option<Rc<Path>> {
-        if self.len() >= self.front {
+    fn next(&mut self) -> Option<Path> {
+        if self.front + self.back >= self.len() {
             None
         } else {
-            let item = self.path[self.front].clone();
+            let id = self.chain[self.front];
             self.front += 1;
-            Some(item)
+            Some(Path { arena: self.arena.clone(), id })
         }
     }
 }
 
-impl<'a> ExactSizeIterator for BiPathIter<'a> {
+impl ExactSizeIterator for BiPathIter {
 
     fn len(&self) -> usize {
-        self.path.len()
+        self.chain.len()
     }
 }
 
-impl<'a> FusedIterator for BiPathIter<'a> {}
+impl FusedIterator for BiPathIter {}
 
-impl<'a> DoubleEndedIterator for BiPathIter<'a> {
+impl DoubleEndedIterator for BiPathIter {
 
-    fn next_back(&mut self) -> Option<Rc<Path>> {
-        if self.len() >= self.back {
+    fn next_back(&mut self) -> Option<Path> {
+        if self.front + self.back >= self.len() {
             None
         } else {
-            let item = self.path[self.len() - self.back - 1].clone();
+            let id = self.chain[self.len() - self.back - 1];
             self.back += 1;
-            Some(item)
+            Some(Path { arena: self.arena.clone(), id })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a path from root-to-leaf node names, e.g. `path(&["a", "b"])`
+    /// is the path "a/b" with "a" as root.
+    fn path(names: &[&str]) -> Path {
+        let mut iter = names.iter();
+        let mut p = Path::new(iter.next().unwrap().to_string());
+
+        for name in iter {
+            p = Path::new_in_path(name.to_string(), &p);
+        }
+
+        p
+    }
+
+    /// Asserts that `$a` and `$b` compare as `$order`, and that `cmp`,
+    /// the reversed comparison and the `<`/`>`/`==` operators all agree.
+    macro_rules! assert_order {
+        ($a:expr, $b:expr, Equal) => {
+            assert_eq!($a.cmp(&$b), Ordering::Equal);
+            assert_eq!($b.cmp(&$a), Ordering::Equal);
+            assert!($a == $b);
+            assert!(!($a < $b));
+            assert!(!($a > $b));
+        };
+        ($a:expr, $b:expr, Less) => {
+            assert_eq!($a.cmp(&$b), Ordering::Less);
+            assert_eq!($b.cmp(&$a), Ordering::Greater);
+            assert!($a < $b);
+            assert!($b > $a);
+            assert!($a != $b);
+        };
+        ($a:expr, $b:expr, Greater) => {
+            assert_eq!($a.cmp(&$b), Ordering::Greater);
+            assert_eq!($b.cmp(&$a), Ordering::Less);
+            assert!($a > $b);
+            assert!($b < $a);
+            assert!($a != $b);
+        };
+    }
+
+    #[test]
+    fn equal_paths() {
+        assert_order!(path(&["a"]), path(&["a"]), Equal);
+        assert_order!(path(&["a", "b", "c"]), path(&["a", "b", "c"]), Equal);
+    }
+
+    #[test]
+    fn prefix_is_less() {
+        assert_order!(path(&["a"]), path(&["a", "b"]), Less);
+        assert_order!(path(&["a", "b"]), path(&["a", "b", "c"]), Less);
+    }
+
+    #[test]
+    fn sibling_divergence() {
+        assert_order!(path(&["a", "b"]), path(&["a", "c"]), Less);
+        assert_order!(path(&["x", "z"]), path(&["y", "a"]), Less);
+    }
+
+    #[test]
+    fn node_count_must_not_dominate_lexicographic_order() {
+        // A buggy `Ord` that compares node counts first would rank
+        // `["b"]` below `["a", "a"]`; lexicographically "b" > "a" wins.
+        assert_order!(path(&["b"]), path(&["a", "a"]), Greater);
+    }
+
+    #[test]
+    fn repeated_names_are_interned_once() {
+        let p = path(&["pkg", "pkg", "pkg"]);
+        assert_eq!(p.arena.borrow().strings.len(), 1);
+    }
+}
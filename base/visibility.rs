@@ -0,0 +1,288 @@
+use std::cmp::Ordering;
+
+use super::{Object, Path, Service, Tier};
+
+/// Visibility of objects and services.
+pub enum Visibility {
+
+    /// Visible to everything from everywhere.
+    Public,
+
+    /// Visible only to services and sub-objects and their services.
+    Internal,
+
+    /// Visible only for parent object and services at current hierarchy level.
+    Private,
+
+    /// Visible only to the named object subtree and nothing else, the
+    /// equivalent of Rust's `pub(in some::path)`.
+    Restricted(Path),
+}
+
+impl Visibility {
+
+    /// Rank used to compare visibilities on the `Public > Internal >
+    /// Private` lattice; higher ranks are more permissive. `Restricted`
+    /// is ranked alongside `Private`, since outside of its named subtree
+    /// it is just as unreachable.
+    pub(crate) fn rank(&self) -> u8 {
+        match *self {
+            Visibility::Public          => 2,
+            Visibility::Internal        => 1,
+            Visibility::Private         => 0,
+            Visibility::Restricted(_)   => 0,
+        }
+    }
+
+    /// The more restrictive (lattice-minimum) of the two visibilities.
+    /// `self` is the running effective visibility accumulated from the
+    /// ancestors seen so far, `other` the declaration of the next
+    /// (strictly deeper) object in the chain.
+    ///
+    /// A plain rank comparison is not enough: `Private` and
+    /// `Restricted(_)` share a rank, so an outer `Private` and an inner
+    /// `Restricted(p)` tie, yet they are not interchangeable -- `p` is a
+    /// real, validated constraint that a bare `Private` cannot represent.
+    /// On such a tie, whichever side carries a concrete `Restricted`
+    /// path wins, since that is always more specific than a plain
+    /// `Private`/`Internal` at the same rank; if both sides do, the
+    /// deeper declaration (`other`) wins, since an object's declarations
+    /// only get more specific as you descend its hierarchy.
+    fn min(self, other: Visibility) -> Visibility {
+        match self.rank().cmp(&other.rank()) {
+            Ordering::Less      => self,
+            Ordering::Greater   => other,
+            Ordering::Equal     => match (&self, &other) {
+                (Visibility::Restricted(_), Visibility::Restricted(_)) => other,
+                (Visibility::Restricted(_), _)                         => self,
+                _                                                      => other,
+            },
+        }
+    }
+}
+
+pub(crate) fn tier_to_visibility(tier: Tier) -> Visibility {
+    match tier {
+        Tier::Public    => Visibility::Public,
+        Tier::Internal  => Visibility::Internal,
+        Tier::Private   => Visibility::Private,
+    }
+}
+
+impl Object {
+
+    /// Computes the effective visibility of the service or sub-object
+    /// named by the last node of `path`, where every node before it
+    /// names a sub-object nested below `self` (in root-to-leaf order).
+    ///
+    /// This is the lattice-minimum of the item's own declared visibility
+    /// and the effective visibility of every object enclosing it: an
+    /// item marked `Public` inside a `Private` sub-object is not actually
+    /// reachable from outside, the same way rustc's effective visibility
+    /// clamps an over-eager `pub` to whatever its module actually allows.
+    ///
+    /// Returns `None` if `path` does not resolve to an existing service
+    /// or sub-object under `self`.
+    pub fn effective_visibility(&self, path: &Path) -> Option<Visibility> {
+        let chain: Vec<_> = path.bi_iter().collect();
+        let mut effective = Visibility::Public;
+        let mut current = self;
+
+        for (i, node) in chain.iter().enumerate() {
+            let is_leaf = i + 1 == chain.len();
+
+            if is_leaf {
+                let declared = current.declared_service_visibility(&node.name())
+                    .or_else(|| current.declared_sub_object_visibility(&node.name())
+                        .map(|(_, vis)| vis))?;
+                return Some(effective.min(declared));
+            }
+
+            let (tier, declared) = current.declared_sub_object_visibility(&node.name())?;
+            effective = effective.min(declared);
+            current = current.sub_object_at(tier, &node.name())?;
+        }
+
+        None
+    }
+
+    /// Whether `own` (the declared visibility of the item that `path`
+    /// names, resolved below `self` as in `effective_visibility`) is a
+    /// "useless" declaration, i.e. one whose effective visibility at
+    /// `path` is more restrictive than what was written.
+    pub fn has_useless_visibility_declaration(&self, path: &Path, own: &Visibility)
+            -> bool {
+        match self.effective_visibility(path) {
+            Some(effective) => effective.rank() < own.rank(),
+            None            => false,
+        }
+    }
+}
+
+/// Identifies what kind of item a `can_access` query is about, purely so
+/// callers (and future diagnostics) have something concrete to name; the
+/// reachability rules below are currently the same for either kind.
+pub enum ItemRef<'a> {
+
+    /// The item being reached is a service.
+    Service(&'a Service),
+
+    /// The item being reached is a sub-object.
+    SubObject(&'a Object),
+}
+
+/// Decides whether code located at `requester` may reference
+/// `target_item`, declared at `target_path` (the path of the object
+/// that directly encloses it) with visibility `declared`.
+///
+/// This is rustc's cross-crate privacy check, ported to this crate's own
+/// three-tier-plus-restricted lattice: `declared` should be the result
+/// of running the effective-visibility pass
+/// (`Object::effective_visibility`) over the item, not just its own
+/// un-clamped declaration, so a technically-`Public` service sitting
+/// inside a `Private` object is correctly treated as unreachable from
+/// outside that object.
+///
+/// - `Public` is always reachable.
+/// - `Internal` is reachable only when `requester` is `target_path`
+///   itself or somewhere in the subtree below it.
+/// - `Private` is reachable only from the immediate parent object of
+///   `target_path`, or from a sibling service/sub-object declared at
+///   `target_path` itself.
+/// - `Restricted(p)` is reachable only when `requester` has `p` as a
+///   prefix, the same as Rust's `pub(in p)`.
+pub fn can_access(requester: &Path, _target_item: &ItemRef, target_path: &Path,
+        declared: Visibility) -> bool {
+    match declared {
+        Visibility::Public     => true,
+        Visibility::Internal   => target_path.is_prefix_of(requester),
+        Visibility::Private    => {
+            let parent = target_path.iter().nth(1);
+            requester == target_path || parent.as_ref() == Some(requester)
+        },
+        Visibility::Restricted(ref p) => p.is_prefix_of(requester),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// Object with every collection empty, named `name`.
+    fn empty_object(name: &str) -> Object {
+        Object {
+            name            : name.to_string(),
+            pubsrv          : BTreeSet::new(),
+            intsrv          : BTreeSet::new(),
+            privsrv         : BTreeSet::new(),
+            ints            : BTreeSet::new(),
+            srvnames        : BTreeMap::new(),
+            pubsub          : BTreeMap::new(),
+            intsub          : BTreeMap::new(),
+            privsub         : BTreeMap::new(),
+            srvrestrictions : BTreeMap::new(),
+            subrestrictions : BTreeMap::new(),
+        }
+    }
+
+    fn svc(name: &str) -> Service {
+        Service { name: name.to_string() }
+    }
+
+    /// Builds a `Path` from root-to-leaf node names, e.g. `path(&["a",
+    /// "b"])` is "a/b" with "a" as the root.
+    fn path(names: &[&str]) -> Path {
+        let mut iter = names.iter();
+        let mut p = Path::new(iter.next().unwrap().to_string());
+
+        for name in iter {
+            p = Path::new_in_path(name.to_string(), &p);
+        }
+
+        p
+    }
+
+    #[test]
+    fn public_is_reachable_from_anywhere() {
+        let item = svc("s");
+        let target = path(&["root", "a"]);
+
+        for requester in &[path(&["root"]), path(&["root", "a"]),
+                path(&["root", "a", "b"]), path(&["root", "c"])] {
+            assert!(can_access(requester, &ItemRef::Service(&item), &target,
+                Visibility::Public));
+        }
+    }
+
+    #[test]
+    fn internal_is_reachable_from_target_and_its_descendants_only() {
+        let item = svc("s");
+        let target = path(&["root", "a"]);
+
+        // Sibling services/sub-objects declared at the target itself.
+        assert!(can_access(&path(&["root", "a"]), &ItemRef::Service(&item),
+            &target, Visibility::Internal));
+        // A descendant of the target.
+        assert!(can_access(&path(&["root", "a", "b"]), &ItemRef::Service(&item),
+            &target, Visibility::Internal));
+        // The parent object is not itself "inside" the target.
+        assert!(!can_access(&path(&["root"]), &ItemRef::Service(&item),
+            &target, Visibility::Internal));
+        // An unrelated branch of the hierarchy.
+        assert!(!can_access(&path(&["root", "c"]), &ItemRef::Service(&item),
+            &target, Visibility::Internal));
+    }
+
+    #[test]
+    fn private_is_reachable_only_from_parent_or_sibling() {
+        let item = svc("s");
+        let target = path(&["root", "a"]);
+
+        // Immediate parent.
+        assert!(can_access(&path(&["root"]), &ItemRef::Service(&item),
+            &target, Visibility::Private));
+        // Sibling service/sub-object declared at the target itself.
+        assert!(can_access(&path(&["root", "a"]), &ItemRef::Service(&item),
+            &target, Visibility::Private));
+        // An unrelated branch of the hierarchy.
+        assert!(!can_access(&path(&["root", "c"]), &ItemRef::Service(&item),
+            &target, Visibility::Private));
+        // A descendant of the target is not the parent/sibling rule.
+        assert!(!can_access(&path(&["root", "a", "b"]), &ItemRef::Service(&item),
+            &target, Visibility::Private));
+    }
+
+    #[test]
+    fn restricted_is_reachable_only_within_its_named_subtree() {
+        let item = svc("s");
+        let target = path(&["root", "a"]);
+        let restriction = path(&["root", "a"]);
+
+        // The restricted subtree's root itself.
+        assert!(can_access(&path(&["root", "a"]), &ItemRef::Service(&item),
+            &target, Visibility::Restricted(restriction.clone())));
+        // A descendant of the restricted subtree.
+        assert!(can_access(&path(&["root", "a", "b"]), &ItemRef::Service(&item),
+            &target, Visibility::Restricted(restriction.clone())));
+        // An unrelated branch of the hierarchy.
+        assert!(!can_access(&path(&["root", "c"]), &ItemRef::Service(&item),
+            &target, Visibility::Restricted(restriction.clone())));
+        // The restricted subtree's own parent.
+        assert!(!can_access(&path(&["root"]), &ItemRef::Service(&item),
+            &target, Visibility::Restricted(restriction)));
+    }
+
+    #[test]
+    fn effective_visibility_clamps_public_item_nested_in_private_ancestor() {
+        let mut root = empty_object("root");
+        let mut a = empty_object("a");
+        a.insert_service(Tier::Public, Box::new(svc("s")), None);
+        root.insert_sub_object(Tier::Private, a, None);
+
+        let effective = root.effective_visibility(&path(&["a", "s"]))
+            .expect("service exists at the given path");
+
+        assert_eq!(effective.rank(), Visibility::Private.rank());
+    }
+}